@@ -0,0 +1,77 @@
+//! Helpers for constructing the errors returned throughout this crate.
+//!
+//! With the default `std` feature, these build plain `std::io::Error`s, as
+//! before. Without it (the `alloc`-only, `no_std` build), `std::io::Error`
+//! doesn't exist, so [`Error`] becomes this crate's own `core`-compatible
+//! type instead.
+
+/// The error type used throughout this crate.
+#[cfg(feature = "std")]
+pub type Error = std::io::Error;
+
+/// A `core`-compatible stand-in for `std::io::Error`, used when this crate
+/// is built without the `std` feature.
+#[cfg(not(feature = "std"))]
+#[derive(Debug)]
+pub struct Error(alloc::string::String);
+
+#[cfg(not(feature = "std"))]
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Error {
+    #[doc(hidden)]
+    pub fn __new(message: alloc::string::String) -> Error {
+        Error(message)
+    }
+}
+
+/// Shorthand for `Result<T, Error>`, used throughout this crate.
+#[cfg(feature = "std")]
+pub type Result<T> = std::io::Result<T>;
+
+/// Shorthand for `Result<T, Error>`, used throughout this crate.
+#[cfg(not(feature = "std"))]
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Builds an [`Error`] from a `format!`-style message.
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! err {
+    ($($arg:tt)*) => {
+        ::std::io::Error::new(::std::io::ErrorKind::InvalidData, format!($($arg)*))
+    };
+}
+
+/// Builds an [`Error`] from a `format!`-style message.
+#[cfg(not(feature = "std"))]
+#[macro_export]
+macro_rules! err {
+    ($($arg:tt)*) => {
+        $crate::ar::error::Error::__new(alloc::format!($($arg)*))
+    };
+}
+
+/// Returns early from the current function with an error built from
+/// [`err!`].
+#[macro_export]
+macro_rules! bail {
+    ($($arg:tt)*) => {
+        return Err($crate::err!($($arg)*))
+    };
+}
+
+/// Returns early from the current function with an error unless the given
+/// condition holds.
+#[macro_export]
+macro_rules! ensure {
+    ($cond:expr, $($arg:tt)*) => {
+        if !($cond) {
+            $crate::bail!($($arg)*);
+        }
+    };
+}
@@ -0,0 +1,27 @@
+//! A minimal write-side I/O shim, used so that [`super::write::GnuBuilder`]
+//! can run on `#![no_std]` targets (behind the `alloc` feature, with the
+//! default `std` feature turned off) as well as on top of `std::io::Write`.
+//!
+//! This only abstracts the writing half of `std::io`; there is no `core`
+//! equivalent of `std::io::Read`, so the reading side of this crate (the
+//! [`read`](super::read) module, and the identifier data that
+//! [`BsdBuilder`](super::write::BsdBuilder) streams in) stays behind the
+//! `std` feature.
+
+#[cfg(feature = "std")]
+pub(crate) use std::io::Write;
+
+/// A `core`-compatible stand-in for `std::io::Write`, used when this crate
+/// is built without the `std` feature.
+#[cfg(not(feature = "std"))]
+pub trait Write {
+    fn write_all(&mut self, buf: &[u8]) -> super::error::Result<()>;
+}
+
+#[cfg(not(feature = "std"))]
+impl Write for alloc::vec::Vec<u8> {
+    fn write_all(&mut self, buf: &[u8]) -> super::error::Result<()> {
+        self.extend_from_slice(buf);
+        Ok(())
+    }
+}
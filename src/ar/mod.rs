@@ -9,12 +9,49 @@
 //! The API of this crate is meant to be similar to that of the
 //! [`tar`](https://crates.io/crates/tar) crate.
 //!
+//! With the default `std` feature disabled and the `alloc` feature enabled,
+//! [`GnuBuilder`] can emit archives on `#![no_std]` targets (e.g. firmware
+//! or WASM build tooling) that have an allocator but no `std::io`. Reading
+//! archives ([`Archive`]/[`Entry`]), [`BsdBuilder`], and
+//! [`Header::from_metadata`] all need `std::io`/`std::fs` and so stay
+//! behind the `std` feature.
 
 mod error;
+mod io;
+#[cfg(feature = "std")]
+mod read;
 mod write;
 
-use crate::{bail, ensure, err};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::ensure;
+pub use error::{Error, Result};
+#[cfg(not(feature = "std"))]
+pub use io::Write;
+#[cfg(feature = "std")]
+pub use read::{Archive, Entry};
 pub use write::GnuBuilder;
+#[cfg(feature = "std")]
+pub use write::BsdBuilder;
+
+// ========================================================================= //
+
+/// Controls what per-member metadata a builder writes into the archive.
+///
+/// Modeled on the `tar` crate's `HeaderMode`. `Complete` preserves whatever
+/// `mtime`/`uid`/`gid`/`mode` the caller's [`Header`] carries; `Deterministic`
+/// normalizes those fields for every member so that archiving the same
+/// inputs always produces byte-identical output, which matters for
+/// reproducible import-library and static-archive builds.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum HeaderMode {
+    /// Preserve the header fields as given.
+    #[default]
+    Complete,
+    /// Normalize `mtime`, `uid`, `gid`, and `mode` to fixed values.
+    Deterministic,
+}
 
 // ========================================================================= //
 
@@ -24,6 +61,12 @@ const GLOBAL_HEADER: &[u8; GLOBAL_HEADER_LEN] = b"!<arch>\n";
 const GNU_NAME_TABLE_ID: &str = "//";
 const GNU_SYMBOL_LOOKUP_TABLE_ID: &str = "/";
 
+// The BSD variant's long-name field looks like `#1/<N>`, where `N` is the
+// number of bytes of the real identifier prepended to the member's data.
+// Only used by the std-only `BsdBuilder`/`Archive` BSD support.
+#[cfg(feature = "std")]
+const BSD_LONG_NAME_PREFIX: &str = "#1/";
+
 // ========================================================================= //
 
 /// Representation of an archive entry header.
@@ -61,13 +104,42 @@ impl Header {
         self.mode = mode;
     }
 
+    /// Sets the last modification time of this file, as a Unix timestamp.
+    pub fn set_mtime(&mut self, mtime: u64) {
+        self.mtime = mtime;
+    }
+
+    /// Sets the user ID that owns this file.
+    pub fn set_uid(&mut self, uid: u32) {
+        self.uid = uid;
+    }
+
+    /// Sets the group ID that owns this file.
+    pub fn set_gid(&mut self, gid: u32) {
+        self.gid = gid;
+    }
+
+    /// Creates a header for `identifier`, filling `mtime`, `uid`, `gid`,
+    /// `mode`, and `size` from the given filesystem metadata.
+    ///
+    /// On Unix, this uses [`std::os::unix::fs::MetadataExt`] to pick up the
+    /// real `mtime`/`uid`/`gid`/`mode`; on other platforms (e.g. Windows,
+    /// which has no equivalent concepts) those fields fall back to the same
+    /// zero/`0o644` defaults as [`Header::new`].
+    #[cfg(feature = "std")]
+    pub fn from_metadata(identifier: Vec<u8>, meta: &std::fs::Metadata) -> Header {
+        let mut header = Header::new(identifier, meta.len());
+        set_metadata_fields(&mut header, meta);
+        header
+    }
+
     /// Returns the length of the file, in bytes.
     pub fn size(&self) -> u64 {
         self.size
     }
 
     /// Validates the header is somewhat sane against the specification.
-    pub fn validate(&self) -> std::io::Result<()> {
+    pub fn validate(&self) -> Result<()> {
         ensure!(
             num_digits(self.mtime, 10) <= 12,
             "MTime `{}` > 12 digits",
@@ -92,6 +164,21 @@ impl Header {
     }
 }
 
+#[cfg(all(feature = "std", unix))]
+fn set_metadata_fields(header: &mut Header, meta: &std::fs::Metadata) {
+    use std::os::unix::fs::MetadataExt;
+    header.mtime = meta.mtime().max(0) as u64;
+    header.uid = meta.uid();
+    header.gid = meta.gid();
+    header.mode = meta.mode() & 0o7777;
+}
+
+#[cfg(all(feature = "std", not(unix)))]
+fn set_metadata_fields(_header: &mut Header, _meta: &std::fs::Metadata) {
+    // No notion of mtime/uid/gid/mode on this platform; keep the
+    // `Header::new` defaults.
+}
+
 #[inline]
 fn num_digits<N: Into<u64>>(val: N, radix: u64) -> u64 {
     let mut val = val.into();
@@ -106,3 +193,47 @@ fn num_digits<N: Into<u64>>(val: N, radix: u64) -> u64 {
     }
     digits
 }
+
+// ========================================================================= //
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::Header;
+
+    #[test]
+    fn from_metadata_picks_up_filesystem_fields() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("implib-rs-from-metadata-test-{}", std::process::id()));
+        std::fs::write(&path, b"hello world").unwrap();
+        let meta = std::fs::metadata(&path).unwrap();
+
+        let header = Header::from_metadata(b"test.txt".to_vec(), &meta);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(header.identifier(), b"test.txt");
+        assert_eq!(header.size(), meta.len());
+        assert_eq!(header.size(), 11);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert_eq!(header.mode, meta.mode() & 0o7777);
+            assert_eq!(header.uid, meta.uid());
+            assert_eq!(header.gid, meta.gid());
+            assert_eq!(header.mtime, meta.mtime().max(0) as u64);
+        }
+    }
+
+    #[test]
+    fn setters_round_trip() {
+        let mut header = Header::new(b"test.txt".to_vec(), 0);
+        header.set_mtime(123);
+        header.set_uid(456);
+        header.set_gid(789);
+        header.set_mode(0o600);
+        assert_eq!(header.mtime, 123);
+        assert_eq!(header.uid, 456);
+        assert_eq!(header.gid, 789);
+        assert_eq!(header.mode, 0o600);
+    }
+}
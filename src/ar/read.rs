@@ -0,0 +1,300 @@
+use std::io::{self, Read};
+
+use crate::ensure;
+
+use super::{
+    Header, BSD_LONG_NAME_PREFIX, GLOBAL_HEADER, GLOBAL_HEADER_LEN, GNU_NAME_TABLE_ID,
+    GNU_SYMBOL_LOOKUP_TABLE_ID,
+};
+
+// ========================================================================= //
+
+const HEADER_LEN: usize = 60;
+
+fn parse_field(field: &[u8]) -> &[u8] {
+    let end = field
+        .iter()
+        .position(|&b| b == b' ')
+        .unwrap_or(field.len());
+    &field[..end]
+}
+
+fn parse_u64(field: &[u8], radix: u32) -> io::Result<u64> {
+    let field = parse_field(field);
+    let field = if field.is_empty() { b"0" } else { field };
+    let text = std::str::from_utf8(field)
+        .map_err(|_| crate::err!("Header field `{:?}` is not valid UTF-8", field))?;
+    u64::from_str_radix(text, radix)
+        .map_err(|_| crate::err!("Header field `{:?}` is not a valid number", text))
+}
+
+// If `name` (the parsed, not-yet-resolved name field) is a BSD `#1/<N>`
+// long-name reference, returns `N`.
+fn bsd_long_name_len(name: &[u8]) -> Option<u64> {
+    let digits = name.strip_prefix(BSD_LONG_NAME_PREFIX.as_bytes())?;
+    std::str::from_utf8(digits).ok()?.parse().ok()
+}
+
+// ========================================================================= //
+
+/// A streaming reader for Unix archive files.
+///
+/// This mirrors the reading half of the [`tar`](https://crates.io/crates/tar)
+/// crate (and the sibling [`ar`](https://crates.io/crates/ar) crate): call
+/// [`Archive::next_entry`] repeatedly to step through the members of the
+/// archive, and read from the returned [`Entry`] to stream its body without
+/// ever buffering a whole member into memory.
+///
+/// GNU long-identifier references (`/<offset>` into the `//` name-table
+/// member) and BSD long identifiers (`#1/<N>`, with the real identifier
+/// prepended to the member's data) are both resolved transparently, and the
+/// GNU symbol-lookup member (`/`) is skipped automatically.
+pub struct Archive<R: Read> {
+    reader: R,
+    started: bool,
+    name_table: Vec<u8>,
+    // Bytes of the previous member (body + even-byte padding) that have not
+    // yet been consumed, either by an `Entry` the caller dropped early or by
+    // our own handling of the name/symbol-lookup tables.
+    unconsumed: u64,
+}
+
+impl<R: Read> Archive<R> {
+    /// Creates a new archive reader around the given underlying reader. The
+    /// global `!<arch>\n` header is not consumed until the first call to
+    /// [`next_entry`](Archive::next_entry).
+    pub fn new(reader: R) -> Archive<R> {
+        Archive {
+            reader,
+            started: false,
+            name_table: Vec::new(),
+            unconsumed: 0,
+        }
+    }
+
+    fn skip_unconsumed(&mut self) -> io::Result<()> {
+        if self.unconsumed > 0 {
+            io::copy(&mut (&mut self.reader).take(self.unconsumed), &mut io::sink())?;
+            self.unconsumed = 0;
+        }
+        Ok(())
+    }
+
+    // Reads the next raw header off the wire, returning `None` at EOF. The
+    // returned identifier is the raw, not-yet-resolved 16-byte name field.
+    fn read_raw_header(&mut self) -> io::Result<Option<(Vec<u8>, Header)>> {
+        self.skip_unconsumed()?;
+        if !self.started {
+            let mut global = [0u8; GLOBAL_HEADER_LEN];
+            self.reader.read_exact(&mut global)?;
+            ensure!(&global == GLOBAL_HEADER, "Not a Unix archive file");
+            self.started = true;
+        }
+        let mut buf = [0u8; HEADER_LEN];
+        if self.reader.read(&mut buf[..1])? == 0 {
+            return Ok(None);
+        }
+        self.reader.read_exact(&mut buf[1..])?;
+        ensure!(
+            &buf[58..60] == b"\x60\n",
+            "Invalid archive member header terminator"
+        );
+        let identifier = buf[0..16].to_vec();
+        let mtime = parse_u64(&buf[16..28], 10)?;
+        let uid = parse_u64(&buf[28..34], 10)? as u32;
+        let gid = parse_u64(&buf[34..40], 10)? as u32;
+        let mode = parse_u64(&buf[40..48], 8)? as u32;
+        let size = parse_u64(&buf[48..58], 10)?;
+        let mut header = Header::new(Vec::new(), size);
+        header.mtime = mtime;
+        header.uid = uid;
+        header.gid = gid;
+        header.mode = mode;
+        self.unconsumed = size + size % 2;
+        Ok(Some((identifier, header)))
+    }
+
+    // Resolves a raw 16-byte name field (which may be a GNU `/<offset>`
+    // reference into the name table) into the real identifier.
+    fn resolve_identifier(&self, identifier: &[u8]) -> Vec<u8> {
+        if let Some(stripped) = identifier.strip_prefix(b"/") {
+            let digits = parse_field(stripped);
+            if let Ok(offset) = std::str::from_utf8(digits)
+                .unwrap_or("")
+                .parse::<usize>()
+            {
+                let rest = &self.name_table[offset.min(self.name_table.len())..];
+                // Entries are terminated by the 2-byte sequence `"/\n"`, not
+                // a lone `/`: identifiers may themselves contain `/` (e.g.
+                // path-like object-file names), so splitting on the first
+                // `/` byte would truncate those early.
+                let end = rest
+                    .windows(2)
+                    .position(|w| w == b"/\n")
+                    .unwrap_or(rest.len());
+                return rest[..end].to_vec();
+            }
+        }
+        let name = parse_field(identifier);
+        name.strip_suffix(b"/").unwrap_or(name).to_vec()
+    }
+
+    /// Loads the next entry in the archive. Returns `None` once the end of
+    /// the archive has been reached. The GNU name-table (`//`) and
+    /// symbol-lookup table (`/`) members are consumed transparently and
+    /// never surfaced as entries; callers only ever see the "real" members.
+    ///
+    /// Dropping an `Entry` without reading it to completion is fine: its
+    /// remaining bytes (and padding) are skipped automatically on the next
+    /// call to `next_entry`.
+    pub fn next_entry(&mut self) -> Option<io::Result<Entry<'_, R>>> {
+        loop {
+            let (raw_identifier, header) = match self.read_raw_header() {
+                Ok(Some(pair)) => pair,
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            };
+            let name = parse_field(&raw_identifier);
+            if name == GNU_NAME_TABLE_ID.as_bytes() {
+                let mut table = vec![0u8; header.size as usize];
+                if let Err(err) = self.reader.read_exact(&mut table) {
+                    return Some(Err(err));
+                }
+                self.unconsumed -= header.size;
+                self.name_table = table;
+                continue;
+            }
+            if name == GNU_SYMBOL_LOOKUP_TABLE_ID.as_bytes() {
+                // We don't (yet) expose the symbol-lookup table; just skip
+                // over it along with everything else on the next read.
+                continue;
+            }
+            let mut header = header;
+            // The on-disk member size, before any BSD long-name bytes are
+            // stripped out of it below: this (not the adjusted `header.size`)
+            // is what determines whether a trailing padding byte follows.
+            let padded = header.size % 2 != 0;
+            if let Some(name_len) = bsd_long_name_len(name) {
+                if name_len > header.size {
+                    return Some(Err(crate::err!(
+                        "BSD long-name length `{}` exceeds member size `{}`",
+                        name_len,
+                        header.size
+                    )));
+                }
+                let mut identifier = vec![0u8; name_len as usize];
+                if let Err(err) = self.reader.read_exact(&mut identifier) {
+                    return Some(Err(err));
+                }
+                self.unconsumed -= name_len;
+                header.identifier = identifier;
+                header.size -= name_len;
+            } else {
+                header.identifier = self.resolve_identifier(&raw_identifier);
+            }
+            return Some(Ok(Entry {
+                archive: self,
+                header,
+                padded,
+            }));
+        }
+    }
+}
+
+// ========================================================================= //
+
+/// A single member of an archive, as returned by [`Archive::next_entry`].
+///
+/// `Entry` implements [`Read`], streaming the member's body directly out of
+/// the underlying reader; the body is never buffered in full.
+pub struct Entry<'a, R: Read> {
+    archive: &'a mut Archive<R>,
+    header: Header,
+    // Whether this member's on-disk size (before any BSD long-name bytes
+    // were stripped out of `header.size`) was odd, i.e. whether a trailing
+    // padding byte follows the body.
+    padded: bool,
+}
+
+impl<'a, R: Read> Entry<'a, R> {
+    /// Returns the header for this entry.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+}
+
+impl<'a, R: Read> Read for Entry<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // `unconsumed` covers this member's body plus its padding byte; only
+        // ever hand out bytes belonging to the body itself.
+        let body_remaining = self.archive.unconsumed.saturating_sub(self.padded as u64);
+        if body_remaining == 0 {
+            return Ok(0);
+        }
+        let limit = body_remaining.min(buf.len() as u64) as usize;
+        let bytes_read = self.archive.reader.read(&mut buf[..limit])?;
+        self.archive.unconsumed -= bytes_read as u64;
+        Ok(bytes_read)
+    }
+}
+
+// ========================================================================= //
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::Archive;
+    use crate::ar::{BsdBuilder, GnuBuilder, Header};
+
+    #[test]
+    fn gnu_long_name_with_embedded_slashes_round_trip() {
+        // Identifiers may themselves contain `/` (e.g. path-like object-file
+        // names); only the `"/\n"` terminator should end the name-table
+        // entry, not the first embedded `/`.
+        let identifier = b"a/very/long/identifier/with/slashes.txt".to_vec();
+        let mut builder = GnuBuilder::new(Vec::new(), vec![identifier.clone()]).unwrap();
+        builder
+            .append(&Header::new(identifier.clone(), 4), b"data")
+            .unwrap();
+        let bytes = builder.into_inner().unwrap();
+
+        let mut archive = Archive::new(&bytes[..]);
+        let entry = archive.next_entry().unwrap().unwrap();
+        assert_eq!(entry.header().identifier(), identifier.as_slice());
+        assert!(archive.next_entry().is_none());
+    }
+
+    #[test]
+    fn bsd_long_name_round_trip() {
+        let long_identifier = b"a-very-long-identifier-that-does-not-fit.txt".to_vec();
+        let mut builder = BsdBuilder::new(Vec::new()).unwrap();
+        builder
+            .append(
+                &Header::new(long_identifier.clone(), 11),
+                &b"long member"[..],
+            )
+            .unwrap();
+        builder
+            .append(&Header::new(b"short".to_vec(), 5), &b"hello"[..])
+            .unwrap();
+        let bytes = builder.into_inner().unwrap();
+
+        let mut archive = Archive::new(&bytes[..]);
+
+        let mut entry = archive.next_entry().unwrap().unwrap();
+        assert_eq!(entry.header().identifier(), long_identifier.as_slice());
+        assert_eq!(entry.header().size(), 11);
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"long member");
+
+        let mut entry = archive.next_entry().unwrap().unwrap();
+        assert_eq!(entry.header().identifier(), b"short");
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"hello");
+
+        assert!(archive.next_entry().is_none());
+    }
+}
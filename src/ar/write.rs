@@ -0,0 +1,549 @@
+#[cfg(not(feature = "std"))]
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    format,
+    vec::Vec,
+};
+#[cfg(feature = "std")]
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use crate::ensure;
+
+use super::error::Result;
+use super::io::Write;
+use super::{Header, HeaderMode, GLOBAL_HEADER, GNU_NAME_TABLE_ID, GNU_SYMBOL_LOOKUP_TABLE_ID};
+#[cfg(feature = "std")]
+use super::BSD_LONG_NAME_PREFIX;
+
+const HEADER_LEN: u64 = 60;
+
+// Total on-disk size (header + content + even-byte padding) of a member
+// whose content is `content_len` bytes long.
+fn member_total_size(content_len: u64) -> u64 {
+    HEADER_LEN + content_len + content_len % 2
+}
+
+// ========================================================================= //
+
+// Whether an identifier doesn't fit in the 16-byte name field as-is, and so
+// needs one of the long-name extensions (GNU `//` table or BSD `#1/N`).
+fn needs_long_name(identifier: &[u8]) -> bool {
+    identifier.len() > 16 || identifier.contains(&b' ')
+}
+
+fn write_field(writer: &mut impl Write, value: &[u8], width: usize) -> Result<()> {
+    ensure!(
+        value.len() <= width,
+        "Field value `{:?}` is longer than {} bytes",
+        value,
+        width
+    );
+    writer.write_all(value)?;
+    for _ in value.len()..width {
+        writer.write_all(b" ")?;
+    }
+    Ok(())
+}
+
+// Normalizes a header's metadata according to the builder's `HeaderMode`.
+// The identifier and size are never touched: they describe the member's
+// content, not its reproducibility-sensitive metadata.
+fn apply_header_mode(header: &Header, mode: HeaderMode) -> Header {
+    match mode {
+        HeaderMode::Complete => header.clone(),
+        HeaderMode::Deterministic => {
+            let mut header = header.clone();
+            header.mtime = 0;
+            header.uid = 0;
+            header.gid = 0;
+            header.mode = 0o644;
+            header
+        }
+    }
+}
+
+fn write_header_with_name(
+    writer: &mut impl Write,
+    name: &[u8],
+    header: &Header,
+) -> Result<()> {
+    write_field(writer, name, 16)?;
+    write_field(writer, format!("{}", header.mtime).as_bytes(), 12)?;
+    write_field(writer, format!("{}", header.uid).as_bytes(), 6)?;
+    write_field(writer, format!("{}", header.gid).as_bytes(), 6)?;
+    write_field(writer, format!("{:o}", header.mode).as_bytes(), 8)?;
+    write_field(writer, format!("{}", header.size).as_bytes(), 10)?;
+    writer.write_all(b"\x60\n")?;
+    Ok(())
+}
+
+// ========================================================================= //
+
+// A member that has been handed to `GnuBuilder::append` but not yet written
+// out, because its header's final byte offset isn't known until every
+// member (and the tables that precede them) has been accounted for.
+struct PendingMember {
+    header: Header,
+    data: Vec<u8>,
+    symbols: Vec<Vec<u8>>,
+}
+
+/// A structure for building Unix archives (in the common GNU variant
+/// format), that support identifiers longer than 16 bytes by writing them
+/// out to a `//` name-table member.
+///
+/// This structure is created by the [`GnuBuilder::new`] method. Members are
+/// buffered in memory as they're appended and only written out, along with
+/// the `//` name table and (if any member defines symbols) the GNU
+/// symbol-lookup table, when [`into_inner`](GnuBuilder::into_inner) is
+/// called: the lookup table's entries need the final byte offset of each
+/// defining member's header, which isn't known until the whole archive's
+/// layout has been computed.
+///
+/// Because members are buffered rather than streamed straight to the
+/// underlying writer, `GnuBuilder` only needs [`Write`](crate::ar::io::Write)
+/// at the very end, which lets it run on `#![no_std]` targets (with the
+/// `alloc` feature, `std` disabled) emitting into e.g. a `Vec<u8>`.
+pub struct GnuBuilder<W: Write> {
+    writer: W,
+    long_names: BTreeSet<Vec<u8>>,
+    header_mode: HeaderMode,
+    members: Vec<PendingMember>,
+}
+
+impl<W: Write> GnuBuilder<W> {
+    /// Creates a new archive builder with the underlying writer object as
+    /// the destination of all data written. `identifiers` must give the
+    /// complete set of identifiers that will be written to this archive via
+    /// [`append`](GnuBuilder::append).
+    pub fn new(writer: W, identifiers: Vec<Vec<u8>>) -> Result<GnuBuilder<W>> {
+        let long_names = identifiers
+            .into_iter()
+            .filter(|identifier| needs_long_name(identifier))
+            .collect();
+        Ok(GnuBuilder {
+            writer,
+            long_names,
+            header_mode: HeaderMode::default(),
+            members: Vec::new(),
+        })
+    }
+
+    /// Sets the mode used to decide what per-member metadata gets written,
+    /// for example [`HeaderMode::Deterministic`] to produce
+    /// byte-for-byte-reproducible archives. Defaults to
+    /// [`HeaderMode::Complete`].
+    pub fn set_header_mode(&mut self, mode: HeaderMode) {
+        self.header_mode = mode;
+    }
+
+    /// Adds a new entry to this archive.
+    pub fn append(&mut self, header: &Header, data: &[u8]) -> Result<()> {
+        self.append_with_symbols(header, data, Vec::new())
+    }
+
+    /// Adds a new entry to this archive, along with the list of symbol
+    /// names that it exports. The symbols are recorded in the archive's GNU
+    /// symbol-lookup table (`/` member) so the resulting archive can be
+    /// linked directly, without needing `ranlib` to be run over it first.
+    pub fn append_with_symbols(
+        &mut self,
+        header: &Header,
+        data: &[u8],
+        symbols: Vec<Vec<u8>>,
+    ) -> Result<()> {
+        let header = apply_header_mode(header, self.header_mode);
+        header.validate()?;
+        ensure!(
+            !needs_long_name(&header.identifier) || self.long_names.contains(&header.identifier),
+            "Identifier `{:?}` was not included in the set passed to `GnuBuilder::new`",
+            header.identifier
+        );
+        ensure!(
+            data.len() as u64 == header.size,
+            "Size `{}` does not match the number of bytes given `{}`",
+            header.size,
+            data.len()
+        );
+        self.members.push(PendingMember {
+            header,
+            data: data.to_vec(),
+            symbols,
+        });
+        Ok(())
+    }
+
+    // Builds the `//` name-table bytes and the offset each long identifier
+    // occupies within it, from a single pass over `self.members` in append
+    // order. Both must come from the same ordered source: the offsets this
+    // returns are only meaningful against the table bytes returned
+    // alongside them, not against the order `identifiers` was given to
+    // `new()` in.
+    fn name_table(&self) -> (Vec<u8>, BTreeMap<Vec<u8>, u64>) {
+        let mut name_table = Vec::new();
+        let mut short_names = BTreeMap::new();
+        for member in &self.members {
+            let identifier = &member.header.identifier;
+            if needs_long_name(identifier) && !short_names.contains_key(identifier) {
+                short_names.insert(identifier.clone(), name_table.len() as u64);
+                name_table.extend_from_slice(identifier);
+                name_table.extend_from_slice(b"/\n");
+            }
+        }
+        (name_table, short_names)
+    }
+
+    /// Finishes the archive: writes the global header, the GNU
+    /// symbol-lookup table (if any member defined symbols), the `//`
+    /// name-table member (if any identifier needed it), then every member
+    /// that was appended, and returns the underlying writer object.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.writer.write_all(GLOBAL_HEADER)?;
+
+        let symbol_count: usize = self.members.iter().map(|m| m.symbols.len()).sum();
+        let mut offset = super::GLOBAL_HEADER_LEN as u64;
+        if symbol_count > 0 {
+            offset += member_total_size(symbol_table_content_len(&self.members));
+        }
+        let (name_table, short_names) = self.name_table();
+        if !name_table.is_empty() {
+            offset += member_total_size(name_table.len() as u64);
+        }
+
+        // A member only needs an entry in the symbol table if it actually
+        // exports symbols, but every member's header offset still has to be
+        // computed so the ones that *do* export symbols get the right
+        // value.
+        let mut symbol_entries = Vec::new();
+        for member in &self.members {
+            for symbol in &member.symbols {
+                symbol_entries.push((symbol.clone(), offset));
+            }
+            offset += member_total_size(member.header.size);
+        }
+
+        if symbol_count > 0 {
+            write_symbol_table(&mut self.writer, &symbol_entries)?;
+        }
+        if !name_table.is_empty() {
+            let table_header =
+                Header::new(GNU_NAME_TABLE_ID.as_bytes().to_vec(), name_table.len() as u64);
+            write_header_with_name(&mut self.writer, GNU_NAME_TABLE_ID.as_bytes(), &table_header)?;
+            self.writer.write_all(&name_table)?;
+            if !name_table.len().is_multiple_of(2) {
+                self.writer.write_all(b"\n")?;
+            }
+        }
+
+        for member in &self.members {
+            write_member_header(&mut self.writer, &short_names, &member.header)?;
+            self.writer.write_all(&member.data)?;
+            if member.data.len() % 2 != 0 {
+                self.writer.write_all(b"\n")?;
+            }
+        }
+
+        Ok(self.writer)
+    }
+}
+
+fn write_member_header(
+    writer: &mut impl Write,
+    short_names: &BTreeMap<Vec<u8>, u64>,
+    header: &Header,
+) -> Result<()> {
+    if needs_long_name(&header.identifier) {
+        let offset = short_names[&header.identifier];
+        let name = format!("/{}", offset);
+        write_header_with_name(writer, name.as_bytes(), header)
+    } else {
+        write_header_with_name(writer, &header.identifier, header)
+    }
+}
+
+fn symbol_table_content_len(members: &[PendingMember]) -> u64 {
+    let mut names_len = 0u64;
+    let mut count = 0u64;
+    for member in members {
+        for symbol in &member.symbols {
+            names_len += symbol.len() as u64 + 1; // NUL terminator
+            count += 1;
+        }
+    }
+    4 + count * 4 + names_len
+}
+
+fn write_symbol_table(writer: &mut impl Write, entries: &[(Vec<u8>, u64)]) -> Result<()> {
+    let mut content = Vec::new();
+    content.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+    for (_, offset) in entries {
+        content.extend_from_slice(&(*offset as u32).to_be_bytes());
+    }
+    for (name, _) in entries {
+        content.extend_from_slice(name);
+        content.push(0);
+    }
+    let header = Header::new(
+        GNU_SYMBOL_LOOKUP_TABLE_ID.as_bytes().to_vec(),
+        content.len() as u64,
+    );
+    write_header_with_name(writer, GNU_SYMBOL_LOOKUP_TABLE_ID.as_bytes(), &header)?;
+    writer.write_all(&content)?;
+    if content.len() % 2 != 0 {
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+// ========================================================================= //
+
+/// A structure for building Unix archives in the BSD variant format, as
+/// produced by the `ar`/`ranlib` that ship with BSD and macOS, which reject
+/// the GNU extended-name table understood by [`GnuBuilder`].
+///
+/// Identifiers that don't fit in the 16-byte name field (or that contain
+/// spaces) are written using the BSD `#1/<length>` extension: the header's
+/// name field becomes `#1/N`, the real identifier is prepended to the
+/// member's data as its first `N` bytes, and the stored size is increased
+/// by `N` to account for it.
+///
+/// This structure is created by the [`BsdBuilder::new`] method. Unlike
+/// [`GnuBuilder`], it streams members straight to the underlying writer and
+/// so stays behind the `std` feature.
+#[cfg(feature = "std")]
+pub struct BsdBuilder<W: std::io::Write> {
+    writer: W,
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> BsdBuilder<W> {
+    /// Creates a new archive builder with the underlying writer object as
+    /// the destination of all data written.
+    pub fn new(mut writer: W) -> io::Result<BsdBuilder<W>> {
+        writer.write_all(GLOBAL_HEADER)?;
+        Ok(BsdBuilder { writer })
+    }
+
+    /// Adds a new entry to this archive.
+    pub fn append<R: Read>(&mut self, header: &Header, mut data: R) -> io::Result<()> {
+        header.validate()?;
+        if needs_long_name(&header.identifier) {
+            let name_len = header.identifier.len() as u64;
+            let mut extended_header = header.clone();
+            extended_header.size = header.size + name_len;
+            let name = format!("{}{}", BSD_LONG_NAME_PREFIX, name_len);
+            write_header_with_name(&mut self.writer, name.as_bytes(), &extended_header)?;
+            self.writer.write_all(&header.identifier)?;
+        } else {
+            write_header_with_name(&mut self.writer, &header.identifier, header)?;
+        }
+        let actual_size = io::copy(&mut data, &mut self.writer)?;
+        ensure!(
+            actual_size == header.size,
+            "Size `{}` does not match the number of bytes written `{}`",
+            header.size,
+            actual_size
+        );
+        let written = actual_size
+            + if needs_long_name(&header.identifier) {
+                header.identifier.len() as u64
+            } else {
+                0
+            };
+        if written % 2 != 0 {
+            self.writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Unwraps this builder, returning the underlying writer object.
+    pub fn into_inner(self) -> io::Result<W> {
+        Ok(self.writer)
+    }
+}
+
+// ========================================================================= //
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::io::Read;
+
+    use super::{GnuBuilder, GLOBAL_HEADER};
+    use crate::ar::{Archive, Header, HeaderMode};
+
+    #[test]
+    fn deterministic_header_mode_normalizes_dirty_metadata() {
+        let mut dirty_a = Header::new(b"short".to_vec(), 5);
+        dirty_a.set_mtime(123456789);
+        dirty_a.set_uid(1000);
+        dirty_a.set_gid(1000);
+        dirty_a.set_mode(0o755);
+
+        let mut builder_a = GnuBuilder::new(Vec::new(), vec![b"short".to_vec()]).unwrap();
+        builder_a.set_header_mode(HeaderMode::Deterministic);
+        builder_a.append(&dirty_a, b"hello").unwrap();
+        let bytes_a = builder_a.into_inner().unwrap();
+
+        let mut dirty_b = Header::new(b"short".to_vec(), 5);
+        dirty_b.set_mtime(1);
+        dirty_b.set_uid(2);
+        dirty_b.set_gid(3);
+        dirty_b.set_mode(0o600);
+
+        let mut builder_b = GnuBuilder::new(Vec::new(), vec![b"short".to_vec()]).unwrap();
+        builder_b.set_header_mode(HeaderMode::Deterministic);
+        builder_b.append(&dirty_b, b"hello").unwrap();
+        let bytes_b = builder_b.into_inner().unwrap();
+
+        // Two builds from differently-"dirty" input headers must serialize
+        // byte-for-byte identically under `Deterministic` mode.
+        assert_eq!(bytes_a, bytes_b);
+
+        let member_header = &bytes_a[GLOBAL_HEADER.len()..GLOBAL_HEADER.len() + 60];
+        let field = |range: std::ops::Range<usize>| {
+            std::str::from_utf8(&member_header[range])
+                .unwrap()
+                .trim_end()
+                .to_string()
+        };
+        assert_eq!(field(16..28), "0"); // mtime
+        assert_eq!(field(28..34), "0"); // uid
+        assert_eq!(field(34..40), "0"); // gid
+        assert_eq!(field(40..48), "644"); // mode, octal
+    }
+
+    #[test]
+    fn gnu_round_trip() {
+        let mut builder = GnuBuilder::new(
+            Vec::new(),
+            vec![b"short".to_vec(), b"a-very-long-identifier.txt".to_vec()],
+        )
+        .unwrap();
+        builder
+            .append(&Header::new(b"short".to_vec(), 5), b"hello")
+            .unwrap();
+        builder
+            .append(
+                &Header::new(b"a-very-long-identifier.txt".to_vec(), 11),
+                b"long member",
+            )
+            .unwrap();
+        let bytes = builder.into_inner().unwrap();
+
+        let mut archive = Archive::new(&bytes[..]);
+
+        let mut entry = archive.next_entry().unwrap().unwrap();
+        assert_eq!(entry.header().identifier(), b"short");
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"hello");
+
+        let mut entry = archive.next_entry().unwrap().unwrap();
+        assert_eq!(entry.header().identifier(), b"a-very-long-identifier.txt");
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"long member");
+
+        assert!(archive.next_entry().is_none());
+    }
+
+    #[test]
+    fn gnu_symbol_table_offsets() {
+        let mut builder =
+            GnuBuilder::new(Vec::new(), vec![b"a.o".to_vec(), b"b.o".to_vec()]).unwrap();
+        builder
+            .append_with_symbols(&Header::new(b"a.o".to_vec(), 3), b"AAA", vec![b"symbol_a".to_vec()])
+            .unwrap();
+        builder
+            .append_with_symbols(&Header::new(b"b.o".to_vec(), 3), b"BBB", vec![b"symbol_b".to_vec()])
+            .unwrap();
+        let bytes = builder.into_inner().unwrap();
+
+        assert_eq!(&bytes[0..GLOBAL_HEADER.len()], GLOBAL_HEADER);
+        // The symbol table is the first member, right after the global
+        // header; its content starts right after its 60-byte header.
+        let content_start = GLOBAL_HEADER.len() + 60;
+        assert_eq!(&bytes[content_start - 2..content_start], b"\x60\n");
+        let count = u32::from_be_bytes(bytes[content_start..content_start + 4].try_into().unwrap());
+        assert_eq!(count, 2);
+        let offset_a = u32::from_be_bytes(
+            bytes[content_start + 4..content_start + 8]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+        let offset_b = u32::from_be_bytes(
+            bytes[content_start + 8..content_start + 12]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+
+        // Each recorded offset must point directly at the header of the
+        // member that actually defines the symbol.
+        assert_eq!(&bytes[offset_a..offset_a + 3], b"a.o");
+        assert_eq!(&bytes[offset_a + 58..offset_a + 60], b"\x60\n");
+        assert_eq!(&bytes[offset_b..offset_b + 3], b"b.o");
+        assert_eq!(&bytes[offset_b + 58..offset_b + 60], b"\x60\n");
+
+        // And the archive still reads back normally via `Archive`, which
+        // skips the symbol table transparently.
+        let mut archive = Archive::new(&bytes[..]);
+        let mut entry = archive.next_entry().unwrap().unwrap();
+        assert_eq!(entry.header().identifier(), b"a.o");
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"AAA");
+
+        let mut entry = archive.next_entry().unwrap().unwrap();
+        assert_eq!(entry.header().identifier(), b"b.o");
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"BBB");
+
+        assert!(archive.next_entry().is_none());
+    }
+
+    #[test]
+    fn gnu_round_trip_out_of_order_append() {
+        // Appends in the *opposite* order from the `identifiers` list given
+        // to `new()`: the on-disk name-table offsets must still line up with
+        // the member each one actually describes, regardless of append order.
+        let mut builder = GnuBuilder::new(
+            Vec::new(),
+            vec![
+                b"a-very-long-identifier-a.txt".to_vec(),
+                b"a-very-long-identifier-b.txt".to_vec(),
+            ],
+        )
+        .unwrap();
+        builder
+            .append(
+                &Header::new(b"a-very-long-identifier-b.txt".to_vec(), 1),
+                b"B",
+            )
+            .unwrap();
+        builder
+            .append(
+                &Header::new(b"a-very-long-identifier-a.txt".to_vec(), 1),
+                b"A",
+            )
+            .unwrap();
+        let bytes = builder.into_inner().unwrap();
+
+        let mut archive = Archive::new(&bytes[..]);
+        let mut entry = archive.next_entry().unwrap().unwrap();
+        assert_eq!(entry.header().identifier(), b"a-very-long-identifier-b.txt");
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"B");
+
+        let mut entry = archive.next_entry().unwrap().unwrap();
+        assert_eq!(entry.header().identifier(), b"a-very-long-identifier-a.txt");
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"A");
+
+        assert!(archive.next_entry().is_none());
+    }
+}